@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Errors produced while aggregating a measurements file.
+///
+/// In `--strict` mode the first error aborts aggregation and is returned to
+/// the caller; the lenient default instead skips the offending line and
+/// keeps going, so the binary stays usable against messy real-world input.
+#[derive(Debug)]
+pub enum ObrsError {
+    /// The underlying read failed.
+    Io(std::io::Error),
+    /// A line had content that didn't parse as `station;reading`.
+    MalformedLine { offset: usize },
+    /// A line was missing the `;` separator between station and reading.
+    MissingSeparator { offset: usize },
+    /// A line's station name was not valid UTF-8.
+    InvalidUtf8 { offset: usize },
+    /// A `--quantiles` entry wasn't a valid percentile like `p50` or `99.9`.
+    InvalidQuantile { spec: String },
+}
+
+impl fmt::Display for ObrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObrsError::Io(err) => write!(f, "I/O error: {err}"),
+            ObrsError::MalformedLine { offset } => write!(f, "malformed line at offset {offset}"),
+            ObrsError::MissingSeparator { offset } => {
+                write!(f, "missing ';' separator at offset {offset}")
+            }
+            ObrsError::InvalidUtf8 { offset } => write!(f, "invalid UTF-8 at offset {offset}"),
+            ObrsError::InvalidQuantile { spec } => {
+                write!(f, "invalid quantile {spec:?}: must be a number in (0, 100]")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObrsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObrsError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ObrsError {
+    fn from(err: std::io::Error) -> Self {
+        ObrsError::Io(err)
+    }
+}
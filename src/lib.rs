@@ -0,0 +1,766 @@
+//! Aggregates "station;reading" measurement lines into a per-station
+//! min/mean/max (and optional quantile) summary.
+
+mod error;
+mod pack;
+
+use hashbrown::HashMap;
+use hashbrown::hash_map::RawEntryMut;
+use memmap2::Mmap;
+use rustc_hash::FxBuildHasher;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub use error::ObrsError;
+pub use pack::{aggregate_packed, pack};
+
+/// Default size of a single aggregation job, in bytes. Jobs are pulled from a
+/// shared queue by whichever worker thread is free, so a skewed station
+/// distribution or an unlucky page fault on one thread no longer stalls the
+/// whole run. Override with `OBRS_JOB_BYTES` or `--job-size <bytes>`.
+const DEFAULT_JOB_BYTES: usize = 4 * 1024 * 1024;
+
+/// Buffer size for the streaming reader path, used when the input can't be
+/// mmapped (stdin, a FIFO, a pipe from a decompressor).
+const CAPACITY_READER: usize = 128 * 1024;
+
+// Readings are parsed as tenths of a degree in [-999.9, 999.9], so the whole
+// domain fits in roughly 20000 integer buckets - cheap enough to track an
+// exact per-station histogram instead of just min/mean/max.
+const READING_MIN: i32 = -9999;
+const READING_MAX: i32 = 9999;
+const READING_OFFSET: i32 = -READING_MIN;
+const READING_BUCKETS: usize = (READING_MAX - READING_MIN + 1) as usize;
+
+struct StationStats {
+    min: i32,
+    max: i32,
+    total: i64,
+    count: usize,
+    // Only allocated when quantiles were actually requested: at ~80KB per
+    // station this would otherwise be a large unconditional memory and
+    // per-line cost paid even by callers who only want min/mean/max.
+    counts: Option<Box<[u32]>>,
+}
+
+impl StationStats {
+    fn new(reading: i32, track_quantiles: bool) -> Self {
+        let counts = track_quantiles.then(|| {
+            let mut counts = vec![0u32; READING_BUCKETS].into_boxed_slice();
+            counts[(reading + READING_OFFSET) as usize] = 1;
+            counts
+        });
+        StationStats {
+            min: reading,
+            max: reading,
+            total: reading as i64,
+            count: 1,
+            counts,
+        }
+    }
+
+    fn record(&mut self, reading: i32) {
+        if reading < self.min {
+            self.min = reading;
+        } else if reading > self.max {
+            self.max = reading;
+        }
+        self.total += reading as i64;
+        self.count += 1;
+        if let Some(counts) = &mut self.counts {
+            counts[(reading + READING_OFFSET) as usize] += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &StationStats) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.total += other.total;
+        self.count += other.count;
+        if let (Some(counts), Some(other_counts)) = (&mut self.counts, &other.counts) {
+            for (bucket, other_bucket) in counts.iter_mut().zip(other_counts.iter()) {
+                *bucket += other_bucket;
+            }
+        }
+    }
+
+    /// Exact value at quantile `q` (0.0..=1.0), found by walking a running
+    /// prefix sum over the histogram until it reaches the target rank.
+    ///
+    /// Panics if the `Summary` this station came from wasn't built with at
+    /// least one quantile requested - the histogram backing this is only
+    /// allocated in that case.
+    fn quantile(&self, q: f64) -> f64 {
+        let counts = self.counts.as_deref().expect("quantile() requires aggregating with quantiles requested");
+        let target_rank = ((q * self.count as f64).ceil() as usize).max(1);
+        let mut running = 0usize;
+        for (bucket, &count) in counts.iter().enumerate() {
+            running += count as usize;
+            if running >= target_rank {
+                return (bucket as i32 - READING_OFFSET) as f64 / 10.0;
+            }
+        }
+        self.max as f64 / 10.0
+    }
+}
+
+type StationMap = HashMap::<String, StationStats, FxBuildHasher>;
+
+/// The result of aggregating a measurements file: a min/mean/max (and
+/// optional quantile) summary per station.
+pub struct Summary {
+    stats: StationMap,
+    quantiles: Vec<f64>,
+}
+
+impl Summary {
+    fn new(stats: StationMap, quantiles: Vec<f64>) -> Self {
+        Summary { stats, quantiles }
+    }
+
+    /// Station names seen in the input, in no particular order.
+    pub fn station_names(&self) -> impl Iterator<Item = &str> {
+        self.stats.keys().map(String::as_str)
+    }
+
+    /// Summary for a single station, if it was seen in the input.
+    pub fn station(&self, name: &str) -> Option<StationSummary<'_>> {
+        self.stats.get(name).map(|stats| StationSummary { stats })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.stats.keys().collect();
+        names.sort();
+
+        write!(f, "{{")?;
+        for (count, name) in names.into_iter().enumerate() {
+            if count != 0 {
+                write!(f, ", ")?;
+            }
+            let summary = StationSummary { stats: &self.stats[name] };
+            write!(f, "{name}={:.1}/{:.1}/{:.1}", summary.min(), summary.mean(), summary.max())?;
+            for &q in &self.quantiles {
+                write!(f, "/{:.1}", summary.quantile(q))?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Min/mean/max and quantile accessors for a single station.
+pub struct StationSummary<'a> {
+    stats: &'a StationStats,
+}
+
+impl StationSummary<'_> {
+    // Values are already multiplied by 10 (12.3 stored as 123); apply IEEE
+    // 754 roundTowardPositive (ceiling) before converting back.
+    pub fn min(&self) -> f64 {
+        (self.stats.min as f64).ceil() / 10.0
+    }
+
+    pub fn max(&self) -> f64 {
+        (self.stats.max as f64).ceil() / 10.0
+    }
+
+    pub fn mean(&self) -> f64 {
+        (self.stats.total as f64 / self.stats.count as f64).ceil() / 10.0
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        self.stats.quantile(q)
+    }
+}
+
+// Ingests `mmap` directly into the caller's long-lived `stats` map instead of
+// building and returning a fresh one: with thousands of small work-stealing
+// jobs per thread (chunk0-1), returning a per-job map would mean merging a
+// ~80KB histogram per station on every job boundary instead of once per
+// thread. `base_offset` is mmap's position in the overall file, so errors
+// report an absolute offset.
+fn do_aggregate(
+    mmap: &[u8],
+    stats: &mut StationMap,
+    base_offset: usize,
+    strict: bool,
+    track_quantiles: bool,
+) -> Result<(), ObrsError> {
+    let mut start = 0;
+    while let Some(offset) = find_byte(&mmap[start..], b'\n') {
+        let end = start + offset;
+        if let Err(err) = ingest_line(stats, &mmap[start..end], base_offset + start, track_quantiles) {
+            if strict {
+                return Err(err);
+            }
+        }
+        start = end + 1;
+    }
+
+    // A truncated final line with no trailing newline is recoverable: report
+    // it in strict mode, drop it silently (as before) in lenient mode.
+    if strict && start < mmap.len() {
+        return Err(ObrsError::MalformedLine { offset: base_offset + start });
+    }
+
+    Ok(())
+}
+
+// Word-at-a-time (SWAR) byte search: processes 8 bytes per iteration instead
+// of one, which is a large throughput win on the multi-gigabyte inputs this
+// tool is meant for. Falls back to a scalar scan for the unaligned tail.
+#[inline]
+fn find_byte(haystack: &[u8], needle: u8) -> Option<usize> {
+    const LO: u64 = 0x0101010101010101;
+    const HI: u64 = 0x8080808080808080;
+    let pattern = LO * needle as u64;
+
+    let mut chunks = haystack.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        // Safety: chunks_exact(8) guarantees exactly 8 bytes per chunk.
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        let xored = word ^ pattern;
+        // Zero bytes in `xored` mark a match; this trick (subtract one from
+        // every byte, AND with the complement, keep only the high bits) lights
+        // up the high bit of the lowest zero byte exactly. Borrows can also
+        // light up bytes above it, so only the lowest set bit is used.
+        let hit = xored.wrapping_sub(LO) & !xored & HI;
+        if hit != 0 {
+            return Some(offset + (hit.trailing_zeros() / 8) as usize);
+        }
+        offset += 8;
+    }
+
+    chunks.remainder().iter().position(|&b| b == needle).map(|i| offset + i)
+}
+
+// The histogram in `StationStats` is indexed directly by `reading +
+// READING_OFFSET`, so any reading outside the declared domain must be
+// rejected here rather than trusted to index the array safely.
+#[inline]
+fn validate_reading(reading: i32, offset: usize) -> Result<i32, ObrsError> {
+    if (READING_MIN..=READING_MAX).contains(&reading) {
+        Ok(reading)
+    } else {
+        Err(ObrsError::MalformedLine { offset })
+    }
+}
+
+// Parse a single "station;reading" line and fold it into `stats`. Shared by
+// the mmap fast path and the streaming reader so both paths stay in sync.
+// `offset` is the line's position in the overall input, used only to label
+// errors.
+#[inline]
+fn ingest_line(
+    stats: &mut StationMap,
+    line_bytes: &[u8],
+    offset: usize,
+    track_quantiles: bool,
+) -> Result<(), ObrsError> {
+    let semicolon_pos =
+        find_byte(line_bytes, b';').ok_or(ObrsError::MissingSeparator { offset })?;
+
+    let station = std::str::from_utf8(&line_bytes[..semicolon_pos])
+        .map_err(|_| ObrsError::InvalidUtf8 { offset })?;
+
+    let reading_bytes = &line_bytes[semicolon_pos + 1..];
+    if reading_bytes.is_empty() {
+        return Err(ObrsError::MalformedLine { offset });
+    }
+    let reading = parse_int(reading_bytes).ok_or(ObrsError::MalformedLine { offset })?;
+    let reading = validate_reading(reading, offset)?;
+
+    // Update tracking - use raw_entry to avoid allocating String on lookup
+    match stats.raw_entry_mut().from_key(station) {
+        RawEntryMut::Occupied(mut entry) => entry.get_mut().record(reading),
+        RawEntryMut::Vacant(entry) => {
+            entry.insert(station.to_owned(), StationStats::new(reading, track_quantiles));
+        }
+    }
+    Ok(())
+}
+
+/// Aggregate a reader that can't be mmapped: buffer reads of `CAPACITY_READER`
+/// bytes, run the same line-at-a-time ingestion over each complete line, and
+/// carry any trailing partial line across to the front of the buffer before
+/// the next read.
+fn aggregate_reader<R: Read>(mut reader: R, strict: bool, track_quantiles: bool) -> Result<StationMap, ObrsError> {
+    let mut stats = StationMap::default();
+    let mut buf = vec![0u8; CAPACITY_READER];
+    let mut len = 0;
+    let mut consumed = 0usize;
+
+    loop {
+        let read = reader.read(&mut buf[len..])?;
+        if read == 0 {
+            break;
+        }
+        len += read;
+
+        let mut start = 0;
+        while let Some(newline) = find_byte(&buf[start..len], b'\n') {
+            let end = start + newline;
+            if let Err(err) = ingest_line(&mut stats, &buf[start..end], consumed + start, track_quantiles) {
+                if strict {
+                    return Err(err);
+                }
+            }
+            start = end + 1;
+        }
+
+        // Carry the unfinished tail line to the front before the next refill.
+        consumed += start;
+        let remaining = len - start;
+        buf.copy_within(start..len, 0);
+        len = remaining;
+
+        // A single line longer than the buffer: grow it and keep reading.
+        if len == buf.len() {
+            buf.resize(buf.len() * 2, 0);
+        }
+    }
+
+    // Same truncated-final-line handling as the mmap path above.
+    if strict && len > 0 {
+        return Err(ObrsError::MalformedLine { offset: consumed });
+    }
+
+    Ok(stats)
+}
+
+/// Slice `mmap` into line-aligned jobs of roughly `job_bytes` each. Many more
+/// jobs than threads are produced on purpose: workers pull from this list via
+/// a shared cursor, so a thread that finishes its current job early just
+/// grabs the next one instead of sitting idle while a slower thread catches up.
+fn job_offsets(mmap: &[u8], job_bytes: usize) -> Vec<usize> {
+    let mut offsets = vec![0];
+    let mut offset = job_bytes;
+    while offset < mmap.len() {
+        match mmap[offset..].iter().position(|byte| *byte == b'\n') {
+            Some(next_line) => offset += next_line + 1,
+            None => break,
+        }
+        offsets.push(offset);
+        offset += job_bytes;
+    }
+    offsets.push(mmap.len());
+    offsets
+}
+
+fn merge_into(stats: &mut StationMap, other: StationMap) {
+    for (station, other_stats) in other.into_iter() {
+        stats.entry(station).and_modify(|entry| entry.merge(&other_stats)).or_insert(other_stats);
+    }
+}
+
+/// Aggregate `path` into a [`Summary`] with default settings: the job size
+/// from [`job_bytes_from_env`], no quantiles, and lenient error handling (bad
+/// lines are skipped).
+/// `path` may be `-` to read from stdin.
+pub fn aggregate(path: &str) -> Result<Summary, ObrsError> {
+    aggregate_with_options(path, job_bytes_from_env(), &[], false)
+}
+
+/// Aggregate `path` into a [`Summary`], with control over the per-job byte
+/// budget, which quantiles to compute, and whether a malformed line aborts
+/// aggregation (`strict`) or is skipped (lenient). `path` may be `-` to read
+/// from stdin.
+pub fn aggregate_with_options(
+    path: &str,
+    job_bytes: usize,
+    quantiles: &[f64],
+    strict: bool,
+) -> Result<Summary, ObrsError> {
+    // The per-station histogram is only worth paying for if a quantile was
+    // actually requested; see `StationStats::counts`.
+    let track_quantiles = !quantiles.is_empty();
+
+    let stats = if path == "-" {
+        aggregate_reader(std::io::stdin().lock(), strict, track_quantiles)?
+    } else {
+        let file = File::open(path)?;
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => aggregate_mmap(&mmap, job_bytes, strict, track_quantiles)?,
+            // Not every file-like input supports mmap (FIFOs, /dev/stdin, pipes
+            // from a decompressor); fall back to the buffered reader for those.
+            Err(_) => aggregate_reader(file, strict, track_quantiles)?,
+        }
+    };
+
+    Ok(Summary::new(stats, quantiles.to_vec()))
+}
+
+fn aggregate_mmap(
+    mmap: &[u8],
+    job_bytes: usize,
+    strict: bool,
+    track_quantiles: bool,
+) -> Result<StationMap, ObrsError> {
+    let num_threads = std::thread::available_parallelism().map(Into::into).unwrap_or(1);
+    let offsets = job_offsets(mmap, job_bytes);
+    // Job `i` spans offsets[i]..offsets[i + 1]; a shared cursor hands these
+    // out to whichever worker asks next.
+    let next_job = AtomicUsize::new(0);
+    let job_count = offsets.len() - 1;
+    // In strict mode, the first worker to hit an error flips this so the
+    // others stop pulling new jobs instead of racing to finish the file.
+    let abort = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        for _ in 0..num_threads.min(job_count.max(1)) {
+            let tx = tx.clone();
+            let offsets = &offsets;
+            let next_job = &next_job;
+            let abort = &abort;
+            scope.spawn(move || {
+                let mut local_stats = StationMap::default();
+                // Paired with the job index (not send order) so the reducer
+                // below can pick the error at the lowest file offset instead
+                // of whichever worker happens to finish first.
+                let mut error = None;
+                loop {
+                    if abort.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let job = next_job.fetch_add(1, Ordering::Relaxed);
+                    if job >= job_count {
+                        break;
+                    }
+                    let job_bytes = &mmap[offsets[job]..offsets[job + 1]];
+                    if let Err(err) =
+                        do_aggregate(job_bytes, &mut local_stats, offsets[job], strict, track_quantiles)
+                    {
+                        abort.store(true, Ordering::Relaxed);
+                        error = Some((job, err));
+                        break;
+                    }
+                }
+                tx.send((local_stats, error)).expect("Channel send failure");
+            });
+        }
+        // Early drop here as we need all tx to drop to leave the recv() loop below.
+        drop(tx);
+
+        // Only num_threads partial maps arrive here, so this reduction stays cheap
+        // regardless of how many jobs were handed out above. Jobs are ordered by
+        // file position, so keeping the lowest job index gives the earliest error
+        // regardless of which worker happened to send first.
+        let mut stats = StationMap::default();
+        let mut earliest_error: Option<(usize, ObrsError)> = None;
+        for (local_stats, error) in rx {
+            merge_into(&mut stats, local_stats);
+            if let Some((job, err)) = error {
+                let is_earlier = match &earliest_error {
+                    Some((earliest_job, _)) => job < *earliest_job,
+                    None => true,
+                };
+                if is_earlier {
+                    earliest_error = Some((job, err));
+                }
+            }
+        }
+
+        match earliest_error {
+            Some((_, err)) => Err(err),
+            None => Ok(stats),
+        }
+    })
+}
+
+/// Resolve the per-job byte budget from `OBRS_JOB_BYTES`, falling back to
+/// `DEFAULT_JOB_BYTES` when unset or unparsable.
+pub fn job_bytes_from_env() -> usize {
+    std::env::var("OBRS_JOB_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_JOB_BYTES)
+}
+
+// Fast integer parser - parses "12.3" as 123 (ignoring decimal point).
+// Returns `None` if any byte isn't part of a valid signed decimal (a leading
+// `-`, digits, and at most one `.`), so garbage like "abc" or a
+// partially-numeric "12xyz" is rejected instead of silently parsing to a
+// truncated or zero value.
+#[inline]
+fn parse_int(bytes: &[u8]) -> Option<i32> {
+    let mut result = 0i32;
+    let mut negative = false;
+    let mut saw_digit = false;
+    let mut saw_dot = false;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'-' if i == 0 => negative = true,
+            b'.' if !saw_dot => saw_dot = true, // Just skip the decimal point
+            b'0'..=b'9' => {
+                result = result * 10 + (byte - b'0') as i32;
+                saw_digit = true;
+            }
+            _ => return None,
+        }
+    }
+
+    if !saw_digit {
+        return None;
+    }
+    Some(if negative { -result } else { result })
+}
+
+/// Parse a comma-separated list of quantiles like `p50,p90,p99` into
+/// fractions in (0.0, 1.0]. Part of the public API, so a malformed spec from
+/// an embedder returns an `Err` rather than panicking.
+pub fn parse_quantiles(spec: &str) -> Result<Vec<f64>, ObrsError> {
+    spec.split(',')
+        .map(|part| {
+            let trimmed = part.trim();
+            let digits = trimmed.strip_prefix('p').unwrap_or(trimmed);
+            let invalid = || ObrsError::InvalidQuantile { spec: trimmed.to_string() };
+            let percentile: f64 = digits.parse().map_err(|_| invalid())?;
+            if percentile > 0.0 && percentile <= 100.0 {
+                Ok(percentile / 100.0)
+            } else {
+                Err(invalid())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[inline(always)]
+    fn check_measurements(basename: &str) {
+        let reference_name = format!("samples/{}.out", basename);
+        let actual_name = format!("samples/{}.txt", basename);
+
+        let reference = String::from_utf8(std::fs::read(reference_name).unwrap()).unwrap();
+        let actual = aggregate(&actual_name).unwrap().to_string();
+
+        assert_eq!(reference, actual);
+    }
+
+    // Scratch file for tests that need a real path (aggregate() takes a
+    // filename, not bytes). Named uniquely per call so parallel tests don't
+    // clobber each other's fixtures.
+    static SCRATCH_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn write_scratch_file(contents: &str) -> std::path::PathBuf {
+        let id = SCRATCH_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("obrs-test-{}-{id}.txt", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn streaming_reader_matches_mmap_path() {
+        let contents = "Foo;12.3\nBar;-4.5\nFoo;98.6\nBaz;0.0\n";
+        let path = write_scratch_file(contents);
+
+        let via_mmap = aggregate(path.to_str().unwrap()).unwrap().to_string();
+        let via_reader =
+            Summary::new(aggregate_reader(std::io::Cursor::new(contents), false, false).unwrap(), vec![])
+                .to_string();
+        assert_eq!(via_mmap, via_reader);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn out_of_range_reading_is_skipped_by_default() {
+        let path = write_scratch_file("Foo;12345.6\nBar;12.3\n");
+
+        let summary = aggregate(path.to_str().unwrap()).unwrap();
+        assert!(summary.station("Foo").is_none());
+        assert_eq!(summary.to_string(), "{Bar=12.3/12.3/12.3}\n");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn out_of_range_reading_errors_in_strict_mode() {
+        let path = write_scratch_file("Foo;12345.6\n");
+
+        let result = aggregate_with_options(path.to_str().unwrap(), job_bytes_from_env(), &[], true);
+        assert!(matches!(result, Err(ObrsError::MalformedLine { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn quantile_reports_exact_value() {
+        let path = write_scratch_file("Foo;10\nFoo;20\nFoo;30\nFoo;40\nFoo;50\n");
+
+        let summary =
+            aggregate_with_options(path.to_str().unwrap(), job_bytes_from_env(), &[0.5], false).unwrap();
+        assert_eq!(summary.station("Foo").unwrap().quantile(0.5), 3.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn aggregate_mmap_merges_values_across_job_boundaries() {
+        // A small job size splits this into several work-stealing jobs, so
+        // min/mean/max have to come out right after `merge_into` folds
+        // multiple workers' partial `StationStats` back together.
+        let path = write_scratch_file("Foo;1.0\nFoo;-5.0\nBar;100.0\nFoo;20.0\nBar;50.0\nFoo;0.0\n");
+
+        let summary = aggregate_with_options(path.to_str().unwrap(), 8, &[], false).unwrap();
+
+        let foo = summary.station("Foo").unwrap();
+        assert_eq!(foo.min(), -5.0);
+        assert_eq!(foo.max(), 20.0);
+        assert_eq!(foo.mean(), 4.0);
+
+        let bar = summary.station("Bar").unwrap();
+        assert_eq!(bar.min(), 50.0);
+        assert_eq!(bar.max(), 100.0);
+        assert_eq!(bar.mean(), 75.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn non_numeric_reading_is_skipped_by_default_but_errors_when_strict() {
+        let path = write_scratch_file("Foo;abc\nBar;12xyz\nBaz;4.5\n");
+
+        let summary = aggregate(path.to_str().unwrap()).unwrap();
+        assert!(summary.station("Foo").is_none());
+        assert!(summary.station("Bar").is_none());
+        assert!(summary.station("Baz").is_some());
+
+        let result = aggregate_with_options(path.to_str().unwrap(), job_bytes_from_env(), &[], true);
+        assert!(matches!(result, Err(ObrsError::MalformedLine { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn malformed_line_is_skipped_by_default_but_errors_when_strict() {
+        let path = write_scratch_file("Foo;12.3\nBarNoSeparator\nBaz;4.5\n");
+
+        let summary = aggregate(path.to_str().unwrap()).unwrap();
+        assert!(summary.station("Foo").is_some());
+        assert!(summary.station("Baz").is_some());
+
+        let result = aggregate_with_options(path.to_str().unwrap(), job_bytes_from_env(), &[], true);
+        assert!(matches!(result, Err(ObrsError::MissingSeparator { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn strict_mode_reports_earliest_error_offset() {
+        // An 8-byte job budget splits this into jobs of about two lines each,
+        // so the job with the bad line near the front can finish after later
+        // jobs - the reducer must still report it over the later failure.
+        let lines = "Foo;1.0\nBadEarly\nFoo;2.0\nFoo;3.0\nFoo;4.0\nFoo;5.0\nBadLate\nFoo;6.0\n";
+        let bad_early_offset = lines.find("BadEarly").unwrap();
+        let path = write_scratch_file(lines);
+
+        let result = aggregate_with_options(path.to_str().unwrap(), 8, &[], true);
+        assert!(matches!(result, Err(ObrsError::MissingSeparator { offset }) if offset == bad_early_offset));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn pack_and_aggregate_packed_round_trip() {
+        let text_path = write_scratch_file("Foo;12.3\nBar;-4.5\nFoo;98.6\n");
+        let bin_path = text_path.with_extension("bin");
+
+        pack(text_path.to_str().unwrap(), bin_path.to_str().unwrap()).unwrap();
+
+        let from_text = aggregate(text_path.to_str().unwrap()).unwrap().to_string();
+        let from_bin = aggregate_packed(bin_path.to_str().unwrap(), &[]).unwrap().to_string();
+        assert_eq!(from_text, from_bin);
+
+        std::fs::remove_file(text_path).unwrap();
+        std::fs::remove_file(bin_path).unwrap();
+    }
+
+    #[test]
+    fn aggregate_packed_detects_flipped_byte() {
+        let text_path = write_scratch_file("Foo;12.3\nBar;-4.5\nFoo;98.6\n");
+        let bin_path = text_path.with_extension("bin");
+
+        pack(text_path.to_str().unwrap(), bin_path.to_str().unwrap()).unwrap();
+
+        let mut bytes = std::fs::read(&bin_path).unwrap();
+        let flip_at = bytes.len() - 1;
+        bytes[flip_at] ^= 0xFF;
+        std::fs::write(&bin_path, &bytes).unwrap();
+
+        let result = aggregate_packed(bin_path.to_str().unwrap(), &[]);
+        assert!(result.is_err());
+
+        std::fs::remove_file(text_path).unwrap();
+        std::fs::remove_file(bin_path).unwrap();
+    }
+
+    #[test]
+    fn measurements_1() {
+        check_measurements("measurements-1");
+    }
+
+    #[test]
+    fn measurements_10() {
+        check_measurements("measurements-10");
+    }
+
+    #[test]
+    fn measurements_10000_unique_keys() {
+        check_measurements("measurements-10000-unique-keys");
+    }
+
+    #[test]
+    fn measurements_2() {
+        check_measurements("measurements-2");
+    }
+
+    #[test]
+    fn measurements_20() {
+        check_measurements("measurements-20");
+    }
+
+    #[test]
+    fn measurements_3() {
+        check_measurements("measurements-3");
+    }
+
+    #[test]
+    fn measurements_boundaries() {
+        check_measurements("measurements-boundaries");
+    }
+
+    #[test]
+    fn measurements_complex_utf8() {
+        check_measurements("measurements-complex-utf8");
+    }
+
+    #[test]
+    fn measurements_dot() {
+        check_measurements("measurements-dot");
+    }
+
+    #[test]
+    fn measurements_rounding() {
+        check_measurements("measurements-rounding");
+    }
+
+    #[test]
+    fn measurements_short() {
+        check_measurements("measurements-short");
+    }
+
+    #[test]
+    fn measurements_shortest() {
+        check_measurements("measurements-shortest");
+    }
+}